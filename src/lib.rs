@@ -23,10 +23,12 @@ pub mod n_body {
     const G: f32 = 6.67430e-11;
     const NUM_BODIES: usize = 3;
     const PAN_SPEED: f32 = 300.0;
+    const DIRECT_FORCE_THRESHOLD: usize = 64; //At or below this many bodies, the direct O(N^2) sum is cheaper than building a tree.
 
     pub struct Body {
         pub position: Vec3,
         velocity: Vec3,
+        acceleration: Vec3,
         mass: f32,
         trajectory: Vec<Vec3>,
         name: String,
@@ -38,6 +40,7 @@ pub mod n_body {
             Self {
                 position,
                 velocity,
+                acceleration: Vec3::new(0.0, 0.0, 0.0),
                 mass,
                 trajectory: vec![],
                 name,
@@ -45,41 +48,44 @@ pub mod n_body {
             }
         }
 
-        /// Get the new position of the body.
-       pub fn update(&mut self, dt: f32) {
-            self.position += self.velocity * dt;
-            self.trajectory.push(self.position);
-            if self.trajectory.len() > 500 {
-                self.trajectory.remove(0);
-            }
-        }
-
-        //Apply the force to the body to change velocity
-        pub fn apply_force(&mut self, force: Vec3, dt: f32) {
-            let acceleration = force / self.mass;
-            self.velocity += acceleration * dt;
-        }
-
-        //Draw the body
-        pub fn draw(&self,radius:f32) {
+        //Draw the body. A highlighted body (the one currently picked) is drawn in a distinct color.
+        pub fn draw(&self,radius:f32, highlighted: bool) {
+            let color = if highlighted { YELLOW } else { WHITE };
 
-            draw_sphere(self.position,radius,None, WHITE);
+            draw_sphere(self.position,radius,None, color);
             for window in self.trajectory.windows(2) {
                 if let [p1, p2] = window {
                     draw_line_3d(p1.clone(), p2.clone(), WHITE);
                 }
             }
         }
+
+        pub fn velocity(&self) -> Vec3 {
+            self.velocity
+        }
+
+        pub fn mass(&self) -> f32 {
+            self.mass
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
     }
 
-    //Calculate the force between two bodies
-    fn gravitational_force(body1: &Body, body2: &Body) -> Vec3 {
-        let direction = body2.position - body1.position;
+    //Newtonian gravitational force that a mass at position2 exerts on a mass at position1.
+    fn newtonian_force(position1: Vec3, mass1: f32, position2: Vec3, mass2: f32) -> Vec3 {
+        let direction = position2 - position1;
         let distance = direction.length().max(1.0); // Prevent division by zero
-        let force_magnitude = G * body1.mass * body2.mass / (distance * distance);
+        let force_magnitude = G * mass1 * mass2 / (distance * distance);
         direction.normalize() * force_magnitude
     }
 
+    //Calculate the force between two bodies
+    fn gravitational_force(body1: &Body, body2: &Body) -> Vec3 {
+        newtonian_force(body1.position, body1.mass, body2.position, body2.mass)
+    }
+
     fn kinetic_energy(body: &Body) -> f32 {
         0.5 * body.mass * body.velocity.length_squared()
     }
@@ -89,6 +95,53 @@ pub mod n_body {
         -G * body1.mass * body2.mass / distance
     }
 
+    //Merge two overlapping bodies into one, conserving total momentum and mass. The heavier
+    //body's name is kept, and the trajectories are joined (the first body's points, then the
+    //second's), keeping only the latest 500 points.
+    fn merge_bodies(a: &Body, b: &Body) -> Body {
+        let mass = a.mass + b.mass;
+        let position = (a.position * a.mass + b.position * b.mass) / mass;
+        let velocity = (a.velocity * a.mass + b.velocity * b.mass) / mass;
+        let name = if a.mass >= b.mass { a.name.clone() } else { b.name.clone() };
+
+        let mut merged = Body::new(position, velocity, mass, name);
+        merged.acceleration = (a.acceleration * a.mass + b.acceleration * b.mass) / mass;
+
+        merged.trajectory = a.trajectory.clone();
+        merged.trajectory.extend(b.trajectory.iter().copied());
+        if merged.trajectory.len() > 500 {
+            let excess = merged.trajectory.len() - 500;
+            merged.trajectory.drain(0..excess);
+        }
+
+        merged
+    }
+
+    //Distance along the ray from `ray_origin` (direction assumed normalized) to the nearest
+    //intersection with the sphere, or None if the ray misses it, the sphere is behind it, or the
+    //ray starts inside the sphere.
+    fn ray_sphere_intersection(
+        ray_origin: Vec3,
+        ray_direction: Vec3,
+        sphere_center: Vec3,
+        sphere_radius: f32,
+    ) -> Option<f32> {
+        let offset = ray_origin - sphere_center;
+        let b = offset.dot(ray_direction);
+        let c = offset.length_squared() - sphere_radius * sphere_radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let t = -b - discriminant.sqrt();
+        if t >= 0.0 {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
     //Struct that will be used to calculate the motion of all bodies.
     pub struct Bodies {
         pub bodies: Vec<Body>,
@@ -99,6 +152,11 @@ pub mod n_body {
         pub total_time: f32,
         pub kinetic_energy: f32, //Kinetic energy of the system.
         pub potential_energy: f32,//Potential energy of the system.
+        pub theta: f32, //Barnes-Hut accuracy parameter: smaller is more accurate but slower.
+        pub collisions_enabled: bool, //Whether overlapping bodies merge instead of passing through each other.
+        pub merge_count: usize, //Number of merges that have happened so far.
+        pub selected: Option<usize>, //Index of the body currently picked by the mouse, if any.
+        initialized: bool, //Whether the initial accelerations have been seeded yet.
     }
 
     impl Bodies {
@@ -112,6 +170,11 @@ pub mod n_body {
                 total_time: 0.0,
                 kinetic_energy: 0.0,
                 potential_energy: 0.0,
+                theta: 0.5,
+                collisions_enabled: true,
+                merge_count: 0,
+                selected: None,
+                initialized: false,
             }
         }
 
@@ -207,13 +270,8 @@ pub mod n_body {
             }
 
         }
-        //updates the positions of the bodies and energetics.
+        //updates the energetics of the system. Must be called after step(), which moves the bodies.
         pub fn update(&mut self, dt: f32) {
-            // update the positions of the bodies
-            for body in &mut self.bodies {
-                body.update(dt);
-            }
-            //update energetics
             self.kinetic_energy = self.total_kinetic_energy(); // Energy at the current instant
             self.potential_energy = self.total_potential_energy();
             self.total_kinetic_energy += self.kinetic_energy; //Energy sum over time. This is used to calculate time averaged energy
@@ -223,23 +281,124 @@ pub mod n_body {
             self.time_averaged_potential_energy = self.total_potential_energy / self.total_time;
         }
 
-        //Apply the force on each body due to all other bodies. This must be called before calling body.update(),
-        //because it changes the velocity of each body, which will be used by body.update().
-        pub fn apply_force(&mut self, dt: f32) {
-            for i in 0..self.bodies.len() {
-                let mut total_force = Vec3::new(0.0, 0.0, 0.0);
-                for j in 0..self.bodies.len() {
-                    if i != j {
-                        let force = gravitational_force(&self.bodies[i], &self.bodies[j]);
-                        total_force += force;
+        //Velocity-Verlet integration step: advances every body's position and velocity by dt
+        //while keeping the system's energy bounded instead of drifting like a first-order Euler step.
+        pub fn step(&mut self, dt: f32) {
+            if !self.initialized {
+                let initial_accelerations = self.compute_all_accelerations();
+                for (body, acceleration) in self.bodies.iter_mut().zip(initial_accelerations) {
+                    body.acceleration = acceleration;
+                }
+                self.initialized = true;
+            }
+
+            for body in &mut self.bodies {
+                body.position += body.velocity * dt + 0.5 * body.acceleration * dt * dt;
+                body.trajectory.push(body.position);
+                if body.trajectory.len() > 500 {
+                    body.trajectory.remove(0);
+                }
+            }
+
+            if self.collisions_enabled {
+                self.merge_colliding_bodies();
+            }
+
+            let new_accelerations = self.compute_all_accelerations();
+            for (body, a_new) in self.bodies.iter_mut().zip(new_accelerations) {
+                body.velocity += 0.5 * (body.acceleration + a_new) * dt;
+                body.acceleration = a_new;
+            }
+        }
+
+        //Picks the direct sum for small N (also the correctness oracle for the Barnes-Hut path)
+        //and the O(N log N) tree approximation once it pays off.
+        fn compute_all_accelerations(&self) -> Vec<Vec3> {
+            if self.bodies.len() <= DIRECT_FORCE_THRESHOLD {
+                self.compute_accelerations()
+            } else {
+                self.compute_accelerations_bh()
+            }
+        }
+
+        //The direct O(N^2) pairwise acceleration of every body due to all others.
+        fn compute_accelerations(&self) -> Vec<Vec3> {
+            self.bodies
+                .iter()
+                .enumerate()
+                .map(|(i, body)| {
+                    let mut total_force = Vec3::new(0.0, 0.0, 0.0);
+                    for (j, other) in self.bodies.iter().enumerate() {
+                        if i != j {
+                            total_force += gravitational_force(body, other);
+                        }
                     }
+                    total_force / body.mass
+                })
+                .collect()
+        }
+
+        //Detect overlapping bodies and merge each colliding pair into one, conserving momentum.
+        //Checks body i against every later body, and re-checks it (without advancing i) whenever
+        //a merge happens, since the merged body may now overlap a different neighbor.
+        fn merge_colliding_bodies(&mut self) {
+            let mut i = 0;
+            while i < self.bodies.len() {
+                let collision = (i + 1..self.bodies.len()).find(|&j| {
+                    let distance = (self.bodies[i].position - self.bodies[j].position).length();
+                    distance < self.bodies[i].radius + self.bodies[j].radius
+                });
+
+                match collision {
+                    Some(j) => {
+                        self.bodies[i] = merge_bodies(&self.bodies[i], &self.bodies[j]);
+                        self.bodies.remove(j);
+                        self.merge_count += 1;
+
+                        //Keep the selection pointing at the same body: the one absorbed into i
+                        //is now i itself, and anything after j shifts down by one.
+                        self.selected = match self.selected {
+                            Some(selected) if selected == j => Some(i),
+                            Some(selected) if selected > j => Some(selected - 1),
+                            other => other,
+                        };
+                    }
+                    None => i += 1,
                 }
-                self.bodies[i].apply_force(total_force, dt);
             }
         }
+
+        //Ray-sphere pick: finds the nearest body whose sphere the ray from `ray_origin` along
+        //`ray_direction` intersects, or None if it misses every body.
+        pub fn pick(&self, ray_origin: Vec3, ray_direction: Vec3) -> Option<usize> {
+            self.bodies
+                .iter()
+                .enumerate()
+                .filter_map(|(i, body)| {
+                    ray_sphere_intersection(ray_origin, ray_direction, body.position, body.radius)
+                        .map(|distance| (i, distance))
+                })
+                .min_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).unwrap())
+                .map(|(i, _)| i)
+        }
+
+        //Barnes-Hut approximation of every body's acceleration: builds a fresh octree from the
+        //current positions, then traverses it once per body.
+        fn compute_accelerations_bh(&self) -> Vec<Vec3> {
+            let tree = match octree::Octree::build(&self.bodies) {
+                Some(tree) => tree,
+                None => return Vec::new(),
+            };
+            self.bodies
+                .iter()
+                .enumerate()
+                .map(|(i, body)| tree.force_on(i, body.position, body.mass, self.theta) / body.mass)
+                .collect()
+        }
+
         pub fn draw(&self) {
-            for body in &self.bodies {
-                body.draw(body.radius);
+            for (i, body) in self.bodies.iter().enumerate() {
+                body.draw(body.radius, self.selected == Some(i));
             }
         }
 
@@ -259,4 +418,299 @@ pub mod n_body {
             total_energy
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        //Small deterministic PRNG (an LCG) so the oracle test doesn't need a `rand`
+        //dependency the rest of the crate doesn't have.
+        fn random_unit(seed: &mut u32) -> f32 {
+            *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (*seed >> 8) as f32 / (1u32 << 24) as f32
+        }
+
+        fn random_bodies(n: usize) -> Bodies {
+            let mut seed: u32 = 0xC0FFEE;
+            let mut bodies = Bodies::new();
+            for i in 0..n {
+                let position = Vec3::new(
+                    random_unit(&mut seed) * 2000.0 - 1000.0,
+                    random_unit(&mut seed) * 2000.0 - 1000.0,
+                    random_unit(&mut seed) * 2000.0 - 1000.0,
+                );
+                let mass = 1.0e15 + random_unit(&mut seed) * 1.0e16;
+                bodies.bodies.push(Body::new(position, Vec3::new(0.0, 0.0, 0.0), mass, format!("body{i}")));
+            }
+            bodies
+        }
+
+        //The direct O(N^2) sum is the correctness oracle for the Barnes-Hut approximation: with
+        //enough bodies to force the tree path and a tight `theta`, the two should agree closely.
+        #[test]
+        fn barnes_hut_matches_direct_sum_within_tolerance() {
+            let mut bodies = random_bodies(DIRECT_FORCE_THRESHOLD + 16);
+            bodies.theta = 0.3;
+
+            let direct = bodies.compute_accelerations();
+            let approx = bodies.compute_accelerations_bh();
+
+            for (exact, approx) in direct.iter().zip(approx.iter()) {
+                let error = (*exact - *approx).length();
+                let scale = exact.length().max(1.0);
+                assert!(
+                    error / scale < 0.05,
+                    "direct={exact:?} bh={approx:?} relative error={}",
+                    error / scale
+                );
+            }
+        }
+
+        //merge_bodies must conserve mass and momentum and keep the heavier body's name, since
+        //that's what `merge_colliding_bodies` relies on to fold one body into another.
+        #[test]
+        fn merge_bodies_conserves_mass_and_momentum() {
+            let a = Body::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 2.0, "A".to_string());
+            let b = Body::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0), 3.0, "B".to_string());
+
+            let merged = merge_bodies(&a, &b);
+
+            assert!((merged.mass() - 5.0).abs() < 1e-6);
+
+            let expected_velocity = (a.velocity() * a.mass() + b.velocity() * b.mass()) / merged.mass();
+            assert!((merged.velocity() - expected_velocity).length() < 1e-6);
+
+            let expected_position = (a.position * a.mass() + b.position * b.mass()) / merged.mass();
+            assert!((merged.position - expected_position).length() < 1e-6);
+
+            assert_eq!(merged.name(), "B"); //heavier of the two wins the name
+        }
+
+        //Four bodies where the first and third are at the same position (so they always
+        //collide) and the other two sit far away, making merge_colliding_bodies fold body 2
+        //into body 0 at a known index (i=0, j=2) so the `selected` remap can be pinned down.
+        fn bodies_with_known_collision() -> Bodies {
+            let mut bodies = Bodies::new();
+            bodies.bodies.push(Body::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 1.0e15, "B0".to_string()));
+            bodies.bodies.push(Body::new(Vec3::new(500.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 1.0e15, "B1".to_string()));
+            bodies.bodies.push(Body::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 1.0e15, "B2".to_string()));
+            bodies.bodies.push(Body::new(Vec3::new(-500.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 1.0e15, "B3".to_string()));
+            bodies
+        }
+
+        #[test]
+        fn merge_colliding_bodies_remaps_selected_absorbed_into_survivor() {
+            let mut bodies = bodies_with_known_collision();
+            bodies.selected = Some(2); // the absorbed body (j)
+
+            bodies.merge_colliding_bodies();
+
+            assert_eq!(bodies.merge_count, 1);
+            assert_eq!(bodies.bodies.len(), 3);
+            assert_eq!(bodies.selected, Some(0)); // remapped to the surviving index (i)
+        }
+
+        #[test]
+        fn merge_colliding_bodies_remaps_selected_after_removed_index() {
+            let mut bodies = bodies_with_known_collision();
+            bodies.selected = Some(3); // after the removed index (j), shifts down by one
+
+            bodies.merge_colliding_bodies();
+
+            assert_eq!(bodies.merge_count, 1);
+            assert_eq!(bodies.selected, Some(2));
+        }
+
+        #[test]
+        fn merge_colliding_bodies_leaves_selected_survivor_untouched() {
+            let mut bodies = bodies_with_known_collision();
+            bodies.selected = Some(0); // the surviving index (i) itself
+
+            bodies.merge_colliding_bodies();
+
+            assert_eq!(bodies.merge_count, 1);
+            assert_eq!(bodies.selected, Some(0));
+        }
+    }
+
+    ///Barnes-Hut octree used by `Bodies::compute_accelerations_bh` to approximate gravity in
+    ///O(N log N) instead of summing every pair directly.
+    pub mod octree {
+        use super::*;
+
+        //Below this cell size, stop subdividing and let a leaf hold more than one body instead:
+        //coincident (or near-coincident) positions would otherwise halve `half_width` forever.
+        const MIN_HALF_WIDTH: f32 = 1e-6;
+
+        enum Content {
+            Empty,
+            Leaf(Vec<(usize, Vec3, f32)>),
+            Internal(Box<[Option<Octree>; 8]>),
+        }
+
+        //One node of the tree: a cubic region of space, the total mass and center of mass of
+        //every body beneath it, and either a leaf of bodies (one, or several once the cell is no
+        //wider than MIN_HALF_WIDTH), 8 children, or nothing.
+        pub struct Octree {
+            center: Vec3,
+            half_width: f32,
+            mass: f32,
+            center_of_mass: Vec3,
+            content: Content,
+        }
+
+        impl Octree {
+            //Build a tree enclosing every body in `bodies`, keyed by its index into that slice.
+            pub fn build(bodies: &[Body]) -> Option<Self> {
+                if bodies.is_empty() {
+                    return None;
+                }
+
+                let (center, half_width) = bounding_cube(bodies);
+                let mut root = Octree::empty(center, half_width);
+                for (index, body) in bodies.iter().enumerate() {
+                    root.insert(index, body.position, body.mass);
+                }
+                Some(root)
+            }
+
+            fn empty(center: Vec3, half_width: f32) -> Self {
+                Self {
+                    center,
+                    half_width,
+                    mass: 0.0,
+                    center_of_mass: Vec3::new(0.0, 0.0, 0.0),
+                    content: Content::Empty,
+                }
+            }
+
+            fn insert(&mut self, index: usize, position: Vec3, mass: f32) {
+                //Every ancestor's center of mass is the mass-weighted mean of all bodies beneath
+                //it, so update it on the way down before descending into a child.
+                let new_mass = self.mass + mass;
+                self.center_of_mass = (self.center_of_mass * self.mass + position * mass) / new_mass;
+                self.mass = new_mass;
+
+                let center = self.center;
+                let half_width = self.half_width;
+
+                //Once a cell can no longer be meaningfully subdivided, pile bodies into the same
+                //leaf instead of recursing into ever-smaller children that never separate them.
+                if half_width <= MIN_HALF_WIDTH {
+                    match &mut self.content {
+                        Content::Empty => self.content = Content::Leaf(vec![(index, position, mass)]),
+                        Content::Leaf(entries) => entries.push((index, position, mass)),
+                        Content::Internal(children) => {
+                            insert_into_child(children, center, half_width, index, position, mass)
+                        }
+                    }
+                    return;
+                }
+
+                let existing_leaf = match &mut self.content {
+                    Content::Leaf(entries) => Some(std::mem::take(entries)),
+                    _ => None,
+                };
+
+                if let Some(entries) = existing_leaf {
+                    let mut children = new_children();
+                    for (leaf_index, leaf_position, leaf_mass) in entries {
+                        insert_into_child(&mut children, center, half_width, leaf_index, leaf_position, leaf_mass);
+                    }
+                    insert_into_child(&mut children, center, half_width, index, position, mass);
+                    self.content = Content::Internal(children);
+                    return;
+                }
+
+                match &mut self.content {
+                    Content::Empty => self.content = Content::Leaf(vec![(index, position, mass)]),
+                    Content::Internal(children) => {
+                        insert_into_child(children, center, half_width, index, position, mass)
+                    }
+                    Content::Leaf(_) => unreachable!(),
+                }
+            }
+
+            //Net gravitational force on the body at `position`/`mass` (its own index `index` is
+            //skipped rather than attracting itself). A node is treated as one point mass at its
+            //center of mass once `node_width / distance_to_com < theta`.
+            pub fn force_on(&self, index: usize, position: Vec3, mass: f32, theta: f32) -> Vec3 {
+                match &self.content {
+                    Content::Empty => Vec3::new(0.0, 0.0, 0.0),
+                    Content::Leaf(entries) => entries
+                        .iter()
+                        .filter(|(leaf_index, _, _)| *leaf_index != index)
+                        .map(|(_, leaf_position, leaf_mass)| {
+                            newtonian_force(position, mass, *leaf_position, *leaf_mass)
+                        })
+                        .sum(),
+                    Content::Internal(children) => {
+                        let distance = (self.center_of_mass - position).length().max(1.0);
+                        let node_width = self.half_width * 2.0;
+                        if node_width / distance < theta {
+                            newtonian_force(position, mass, self.center_of_mass, self.mass)
+                        } else {
+                            children
+                                .iter()
+                                .filter_map(|child| child.as_ref())
+                                .map(|child| child.force_on(index, position, mass, theta))
+                                .sum()
+                        }
+                    }
+                }
+            }
+        }
+
+        fn new_children() -> Box<[Option<Octree>; 8]> {
+            Box::new([None, None, None, None, None, None, None, None])
+        }
+
+        fn insert_into_child(
+            children: &mut [Option<Octree>; 8],
+            parent_center: Vec3,
+            parent_half_width: f32,
+            index: usize,
+            position: Vec3,
+            mass: f32,
+        ) {
+            let child_half_width = parent_half_width / 2.0;
+            let octant = octant_of(parent_center, position);
+            let child = children[octant]
+                .get_or_insert_with(|| Octree::empty(octant_center(parent_center, child_half_width, octant), child_half_width));
+            child.insert(index, position, mass);
+        }
+
+        //Which of the 8 octants around `center` a position falls into.
+        fn octant_of(center: Vec3, position: Vec3) -> usize {
+            let mut octant = 0;
+            if position.x >= center.x {
+                octant |= 1;
+            }
+            if position.y >= center.y {
+                octant |= 2;
+            }
+            if position.z >= center.z {
+                octant |= 4;
+            }
+            octant
+        }
+
+        fn octant_center(center: Vec3, child_half_width: f32, octant: usize) -> Vec3 {
+            let sign = |bit: usize| if octant & bit != 0 { child_half_width } else { -child_half_width };
+            center + Vec3::new(sign(1), sign(2), sign(4))
+        }
+
+        //The smallest cube that encloses every body, with a half-width of at least 1.0.
+        fn bounding_cube(bodies: &[Body]) -> (Vec3, f32) {
+            let mut min = bodies[0].position;
+            let mut max = bodies[0].position;
+            for body in bodies.iter().skip(1) {
+                min = min.min(body.position);
+                max = max.max(body.position);
+            }
+            let center = (min + max) / 2.0;
+            let half_width = ((max - min).max_element() / 2.0).max(1.0);
+            (center, half_width)
+        }
+    }
 }
\ No newline at end of file
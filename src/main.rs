@@ -34,11 +34,32 @@
 ////////////////////////////////
 
 use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets::Button};
 use n_body_problem::n_body::Bodies;
 
-const MOVE_SPEED: f32 = 3.6;
 const LOOK_SPEED: f32 = 0.1;
+const THRUST_MAG: f32 = 40.0; //Camera acceleration per second while a movement key is held.
+const DAMPING_COEFF: f32 = 2.0; //Exponential decay rate applied to cam_velocity each frame.
 //const ZOOM_SPEED: f32 = 0.1;
+const MIN_TIME_SCALE: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 16.0;
+const MAX_SUBSTEP_DT: f32 = 1.0 / 60.0; //Largest physics step per substep; larger scaled_dt gets split up to stay stable.
+const FOVY_DEGREES: f32 = 45.0; //Must match Camera3D's default fovy, since picking rays are cast using it.
+
+const TIME_SCALE_SLIDER_ID: u64 = 1;
+
+//Direction of a ray cast from the camera through a screen-space point, built from the camera's
+//own basis vectors so it lines up with what set_camera() actually renders.
+fn mouse_ray_direction(front: Vec3, right: Vec3, up: Vec3, mouse: Vec2) -> Vec3 {
+    let aspect = screen_width() / screen_height();
+    let half_height = (FOVY_DEGREES.to_radians() / 2.0).tan();
+    let half_width = aspect * half_height;
+
+    let ndc_x = (2.0 * mouse.x / screen_width()) - 1.0;
+    let ndc_y = 1.0 - (2.0 * mouse.y / screen_height());
+
+    (front + right * ndc_x * half_width + up * ndc_y * half_height).normalize()
+}
 
 
 fn conf() -> Conf {
@@ -51,12 +72,67 @@ fn conf() -> Conf {
     }
 }
 
+//Reset the simulation from data.json and record how many bodies it started with.
+fn restart_bodies(bodies: &mut Bodies, initial_body_count: &mut usize) {
+    *bodies = Bodies::new();
+    bodies.parse_json("data.json");
+    *initial_body_count = bodies.bodies.len();
+}
+
+//Load an icon texture for the toolbar, falling back to an empty texture if the file is missing.
+async fn load_icon(path: &str) -> Texture2D {
+    match load_texture(path).await {
+        Ok(texture) => texture,
+        Err(e) => {
+            eprintln!("Error loading icon texture {}: {}", path, e);
+            Texture2D::empty()
+        }
+    }
+}
+
+//Draw the pause/play, fast-forward, and restart buttons plus the time-scale slider, wiring them
+//to the same state the keyboard shortcuts use.
+fn draw_toolbar(
+    is_paused: &mut bool,
+    grabbed: &mut bool,
+    time_scale: &mut f32,
+    bodies: &mut Bodies,
+    initial_body_count: &mut usize,
+    pause_icon: &Texture2D,
+    play_icon: &Texture2D,
+    fast_forward_icon: &Texture2D,
+    restart_icon: &Texture2D,
+) {
+    let y = screen_height() - 110.0;
+
+    if Button::new(if *is_paused { play_icon.clone() } else { pause_icon.clone() })
+        .position(vec2(10.0, y))
+        .ui(&mut root_ui())
+    {
+        *is_paused = !*is_paused;
+        *grabbed = !*grabbed;
+        set_cursor_grab(*grabbed);
+        show_mouse(!*grabbed);
+    }
+
+    if Button::new(fast_forward_icon.clone()).position(vec2(60.0, y)).ui(&mut root_ui()) {
+        *time_scale = (*time_scale * 2.0).min(MAX_TIME_SCALE);
+    }
+
+    if Button::new(restart_icon.clone()).position(vec2(110.0, y)).ui(&mut root_ui()) {
+        restart_bodies(bodies, initial_body_count);
+    }
+
+    root_ui().slider(TIME_SCALE_SLIDER_ID, "Time Scale", MIN_TIME_SCALE..MAX_TIME_SCALE, time_scale);
+}
+
 #[macroquad::main(conf)]
 async fn main() {
 
     //Initialize  bodies
     let mut bodies = Bodies::new();
     bodies.parse_json("data.json");
+    let mut initial_body_count = bodies.bodies.len(); //How many bodies the simulation started with, for the status bar.
 
     //axis configuration variables
     let world_up = vec3(0.0, 1.0, 0.0);
@@ -78,14 +154,23 @@ async fn main() {
             bodies.bodies[0].radius + 100.0,
             bodies.bodies[0].radius + 100.0,) ; // initial camera position. Close to one of the bodies.
     let mut position = default_position; //Camera position
+    let mut cam_velocity = vec3(0.0, 0.0, 0.0); //Camera velocity, for inertial "coast and glide" movement.
+    let mut selection_offset = vec3(0.0, 0.0, 0.0); //Camera position relative to the selected body, held fixed while following it.
     let mut last_mouse_position: Vec2 = mouse_position().into();
 
     let mut grabbed = true; // For when the program grabs the mouse. <TAB> will be used to toggle the grab.
     set_cursor_grab(grabbed);
     show_mouse(false);
 
+    //Icon textures for the on-screen toolbar.
+    let pause_icon = load_icon("assets/icons/pause.png").await;
+    let play_icon = load_icon("assets/icons/play.png").await;
+    let fast_forward_icon = load_icon("assets/icons/fast_forward.png").await;
+    let restart_icon = load_icon("assets/icons/restart.png").await;
+
     //let mut zoom: f32 = 1.0; //Initial zoom level
     let mut is_paused: bool = false;
+    let mut time_scale: f32 = 1.0; //Multiplier on dt, driven by the toolbar slider and fast-forward button.
     let mut second:f32 = 0.0;
     let mut fps=0;
 
@@ -120,6 +205,17 @@ async fn main() {
             set_default_camera();
             //print paused on the screen
             draw_text("Paused", 50.0, 50.0, 50.0, BLUE);
+            draw_toolbar(
+                &mut is_paused,
+                &mut grabbed,
+                &mut time_scale,
+                &mut bodies,
+                &mut initial_body_count,
+                &pause_icon,
+                &play_icon,
+                &fast_forward_icon,
+                &restart_icon,
+            );
             next_frame().await;
             continue;
         }
@@ -127,26 +223,31 @@ async fn main() {
 
         //Button to restart the simulation
         if is_key_pressed(KeyCode::R) {
-            bodies = Bodies::new();
-            bodies.parse_json("data.json");
-
+            restart_bodies(&mut bodies, &mut initial_body_count);
         }
         if is_key_pressed(KeyCode::C){
             position = default_position; //reset camera
+            cam_velocity = vec3(0.0, 0.0, 0.0);
+            bodies.selected = None;
         }
-            //Movement
+            //Movement: accumulate thrust from the held keys, then integrate with damping so the
+            //camera coasts and glides instead of teleporting a fixed distance every frame.
+        let mut thrust = vec3(0.0, 0.0, 0.0);
         if is_key_down(KeyCode::Up) ||is_key_down(KeyCode::W) {
-            position += front * MOVE_SPEED;
+            thrust += front;
         }
         if is_key_down(KeyCode::Down) ||is_key_down(KeyCode::S) {
-            position -= front * MOVE_SPEED;
+            thrust -= front;
         }
         if is_key_down(KeyCode::Left) ||is_key_down(KeyCode::A) {
-            position -= right * MOVE_SPEED;
+            thrust -= right;
         }
         if is_key_down(KeyCode::Right) ||is_key_down(KeyCode::D) {
-            position += right * MOVE_SPEED;
+            thrust += right;
         }
+        cam_velocity += thrust * THRUST_MAG * delta;
+        cam_velocity -= cam_velocity * DAMPING_COEFF * delta;
+        position += cam_velocity * delta;
         // Handle mouse wheel zoom
         /*if is_mouse_button_down(MouseButton::Middle) {
             let mouse_wheel_delta: f32 = mouse_wheel().1;
@@ -173,11 +274,34 @@ async fn main() {
         right = front.cross(world_up).normalize();
         up = right.cross(front).normalize();
 
+        //Click to pick a body under the cursor and lock the camera onto it; only available while
+        //the mouse is released, since picking needs real cursor coordinates rather than a look delta.
+        if !grabbed && is_mouse_button_pressed(MouseButton::Left) {
+            let ray_direction = mouse_ray_direction(front, right, up, mouse_position);
+            bodies.selected = bodies.pick(position, ray_direction);
+            if let Some(i) = bodies.selected {
+                selection_offset = position - bodies.bodies[i].position;
+            }
+        }
+
+        //Follow-cam: while a body is selected, keep the camera at its fixed offset from it and
+        //looking straight at it, instead of the usual WASD-driven position and look direction.
+        let target = match bodies.selected {
+            Some(i) if i < bodies.bodies.len() => {
+                position = bodies.bodies[i].position + selection_offset;
+                bodies.bodies[i].position
+            }
+            _ => {
+                bodies.selected = None;
+                position + front
+            }
+        };
+
            // Going 3d!
         set_camera(&Camera3D {
             position: position,
             up: up,
-            target: position + front,
+            target: target,
             ..Default::default()
         });
 
@@ -185,8 +309,16 @@ async fn main() {
 
         //Render bodies.
         bodies.draw();
-        bodies.apply_force(delta); //Change the velocities of the bodies due to G forces.
-        bodies.update(delta); //Update the positions of the bodies.
+
+        //Advance physics by delta*time_scale, split into fixed-size substeps so the integrator
+        //stays stable even at high fast-forward multipliers.
+        let scaled_dt = delta * time_scale;
+        let num_substeps = (scaled_dt / MAX_SUBSTEP_DT).ceil().max(1.0) as usize;
+        let substep_dt = scaled_dt / num_substeps as f32;
+        for _ in 0..num_substeps {
+            bodies.step(substep_dt); //Velocity-Verlet step: advances positions and velocities under gravity.
+            bodies.update(substep_dt); //Update the energetics of the system.
+        }
 
         // Back to screen space, render info text
 
@@ -201,6 +333,38 @@ async fn main() {
             WHITE,
         );
 
+        //Simulation control toolbar: pause/play, fast-forward, restart, and a time-scale slider
+        //for users who don't know the keyboard shortcuts.
+        draw_toolbar(
+            &mut is_paused,
+            &mut grabbed,
+            &mut time_scale,
+            &mut bodies,
+            &mut initial_body_count,
+            &pause_icon,
+            &play_icon,
+            &fast_forward_icon,
+            &restart_icon,
+        );
+
+        //Info panel for the selected body: name, mass, current speed, and its own kinetic energy.
+        if let Some(body) = bodies.selected.and_then(|i| bodies.bodies.get(i)) {
+            let speed = body.velocity().length();
+            let selected_kinetic_energy = 0.5 * body.mass() * speed * speed;
+
+            draw_rectangle(screen_width() - 230.0, 10.0, 220.0, 100.0, DARKGRAY);
+            draw_text(&format!("Selected: {}", body.name()), screen_width() - 220.0, 30.0, 18.0, WHITE);
+            draw_text(&format!("Mass: {:.4e}", body.mass()), screen_width() - 220.0, 50.0, 16.0, WHITE);
+            draw_text(&format!("Speed: {:.4e}", speed), screen_width() - 220.0, 70.0, 16.0, WHITE);
+            draw_text(
+                &format!("Kinetic Energy: {:.4e}", selected_kinetic_energy),
+                screen_width() - 220.0,
+                90.0,
+                16.0,
+                WHITE,
+            );
+        }
+
         //Status bar
         // Draw a rectangle at the bottom of the screen to display status information
         draw_rectangle(
@@ -211,9 +375,15 @@ async fn main() {
             DARKGRAY
         );
 
-        // Display the number of bodies
+        // Display the number of bodies remaining versus how many the simulation started with,
+        // plus how many merges have happened so far.
         draw_text(
-            &format!("Bodies: {}", bodies.bodies.len()),
+            &format!(
+                "Bodies: {}/{} Merges: {}",
+                bodies.bodies.len(),
+                initial_body_count,
+                bodies.merge_count
+            ),
             10.0,
             screen_height() - 30.0,
             17.0,